@@ -18,13 +18,31 @@ pub enum CacheError {
     VersionMismatch,
 }
 
+/// What a lookup produced, keeping a "we already searched and found
+/// nothing" negative entry distinct from "we found a match".
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum CacheValue {
+    Found(ITunesInfos),
+    NotFound,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct CacheEntry {
-    data: ITunesInfos,
+    data: CacheValue,
     #[serde(with = "timestamp_serde")]
     created_at: SystemTime,
 }
 
+/// Result of a [`Cache::get`] lookup. Distinguishes a cached negative
+/// result (`NotFound`) from no cache entry at all (`Absent`), so callers
+/// know whether to skip the network request entirely.
+#[derive(Debug)]
+pub enum CacheLookup<'a> {
+    Found(&'a ITunesInfos),
+    NotFound,
+    Absent,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Cache {
     version: i32,
@@ -34,6 +52,8 @@ pub struct Cache {
     #[serde(skip)]
     max_age: Duration,
     #[serde(skip)]
+    negative_max_age: Duration,
+    #[serde(skip)]
     dirty: bool,
 }
 
@@ -74,20 +94,50 @@ impl Cache {
             .join("apple-music-rpc.cache");
 
         Self {
-            version: 3,
+            version: 4,
             cache_file: cache_path,
             data: HashMap::new(),
             max_age: Duration::from_secs(7 * 24 * 60 * 60), // 1 week default
+            negative_max_age: Duration::from_secs(30 * 60), // 30 minutes default
             dirty: false,
         }
     }
 
-    pub fn get(&mut self, key: String) -> Option<&ITunesInfos> {
+    /// Overrides the on-disk cache location, e.g. when the user configures
+    /// one in `apple-music-rpc.toml`.
+    pub fn set_path(&mut self, path: PathBuf) {
+        self.cache_file = path;
+    }
+
+    /// Overrides the positive/negative entry TTLs, e.g. when the user
+    /// configures them in `apple-music-rpc.toml`.
+    pub fn set_ttls(&mut self, max_age: Duration, negative_max_age: Duration) {
+        self.max_age = max_age;
+        self.negative_max_age = negative_max_age;
+    }
+
+    pub fn get(&mut self, key: &str) -> CacheLookup<'_> {
         self.cleanup_expired();
-        self.data.get(&key).map(|entry| &entry.data)
+        match self.data.get(key).map(|entry| &entry.data) {
+            Some(CacheValue::Found(infos)) => CacheLookup::Found(infos),
+            Some(CacheValue::NotFound) => CacheLookup::NotFound,
+            None => CacheLookup::Absent,
+        }
     }
 
     pub fn set(&mut self, key: String, value: ITunesInfos) {
+        self.insert(key, CacheValue::Found(value));
+    }
+
+    /// Records that a lookup came back empty, so repeated requests for the
+    /// same (likely unmatched) track don't keep hitting the network every
+    /// second. Expires much sooner than a positive hit so a later metadata
+    /// fix can still resolve.
+    pub fn set_not_found(&mut self, key: String) {
+        self.insert(key, CacheValue::NotFound);
+    }
+
+    fn insert(&mut self, key: String, value: CacheValue) {
         let entry = CacheEntry {
             data: value,
             created_at: SystemTime::now(),
@@ -102,9 +152,17 @@ impl Cache {
 
     fn cleanup_expired(&mut self) {
         let now = SystemTime::now();
+        let max_age = self.max_age;
+        let negative_max_age = self.negative_max_age;
+
         self.data.retain(|_, entry| {
+            let ttl = match entry.data {
+                CacheValue::Found(_) => max_age,
+                CacheValue::NotFound => negative_max_age,
+            };
+
             now.duration_since(entry.created_at)
-                .map(|age| age <= self.max_age)
+                .map(|age| age <= ttl)
                 .unwrap_or(false)
         });
     }
@@ -0,0 +1,72 @@
+//! Runtime settings loaded from `~/Library/Preferences/apple-music-rpc.toml`,
+//! falling back to sensible defaults when the file is absent so the app
+//! still runs out of the box.
+
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::structs::{MusicError, DISCORD_CLIENT_ID};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub discord_client_id: String,
+    pub poll_interval_secs: u64,
+    pub reconnect_delay_secs: u64,
+    pub cache_path: Option<PathBuf>,
+    pub cache_ttl_secs: u64,
+    pub negative_cache_ttl_secs: u64,
+    pub prefer_album_artwork: bool,
+    pub show_apple_music_button: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            discord_client_id: DISCORD_CLIENT_ID.to_string(),
+            poll_interval_secs: 1,
+            reconnect_delay_secs: 15,
+            cache_path: None,
+            cache_ttl_secs: 7 * 24 * 60 * 60, // 1 week
+            negative_cache_ttl_secs: 30 * 60, // 30 minutes
+            prefer_album_artwork: true,
+            show_apple_music_button: true,
+        }
+    }
+}
+
+impl Config {
+    /// Loads `~/Library/Preferences/apple-music-rpc.toml`, or falls back to
+    /// `Config::default()` when it doesn't exist.
+    pub fn load() -> Result<Self, MusicError> {
+        match std::fs::read_to_string(config_path()) {
+            Ok(text) => Ok(toml::from_str(&text)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(MusicError::from(e)),
+        }
+    }
+
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_secs(self.poll_interval_secs)
+    }
+
+    pub fn reconnect_delay(&self) -> Duration {
+        Duration::from_secs(self.reconnect_delay_secs)
+    }
+
+    pub fn cache_ttl(&self) -> Duration {
+        Duration::from_secs(self.cache_ttl_secs)
+    }
+
+    pub fn negative_cache_ttl(&self) -> Duration {
+        Duration::from_secs(self.negative_cache_ttl_secs)
+    }
+}
+
+fn config_path() -> PathBuf {
+    std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("Library/Preferences/apple-music-rpc.toml")
+}
@@ -1,18 +1,22 @@
 use discord_rich_presence::{activity, DiscordIpc, DiscordIpcClient};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::{
     signal::unix::{signal, SignalKind},
     time::Duration,
 };
-use url::Url;
 
 mod cache;
+mod config;
+mod metrics;
+mod providers;
 mod structs;
 mod util;
 
-use cache::Cache;
+use cache::{Cache, CacheLookup};
+use config::Config;
+use metrics::Metrics;
+use providers::ArtworkProvider;
 use structs::*;
 use util::*;
 
@@ -21,21 +25,37 @@ struct App {
     client: DiscordIpcClient,
     cache: Cache,
     app_name: ITunesAppName,
+    config: Config,
+    metrics: Metrics,
+    providers: Vec<Box<dyn ArtworkProvider>>,
+    last_signature: Option<TrackSignature>,
+    last_presence: Option<PresenceData>,
 }
 
 impl App {
-    pub fn new(client_id: &str, app_name: ITunesAppName) -> Result<Self, MusicError> {
+    pub fn new(config: Config, app_name: ITunesAppName) -> Result<Self, MusicError> {
         let mut cache = Cache::new();
+        if let Some(cache_path) = config.cache_path.clone() {
+            cache.set_path(cache_path);
+        }
+        cache.set_ttls(config.cache_ttl(), config.negative_cache_ttl());
         let _ = cache.load_cache();
 
-        let client = DiscordIpcClient::new(client_id)
+        let client = DiscordIpcClient::new(&config.discord_client_id)
             .map_err(|e| MusicError::DiscordError(e.to_string()))?;
 
+        let providers = providers::configured_providers(config.prefer_album_artwork);
+
         Ok(App {
             state: AppState::Idle,
             client,
             cache,
             app_name,
+            config,
+            metrics: Metrics::new(),
+            providers,
+            last_signature: None,
+            last_presence: None,
         })
     }
 
@@ -45,6 +65,13 @@ impl App {
                 Ok(()) => {
                     println!("Successfully reconnected to Discord!");
                     self.client = new_client;
+                    self.metrics.record_reconnect();
+                    // The new client starts with no activity set, so forget
+                    // whatever we last compared against; otherwise an
+                    // unchanged track would be seen as "Unchanged" and
+                    // presence would never be re-sent.
+                    self.last_signature = None;
+                    self.last_presence = None;
                     true
                 }
                 Err(_) => false,
@@ -56,80 +83,123 @@ impl App {
     async fn search_album_artwork(
         &mut self,
         props: &ITunesProps,
-        album: bool,
     ) -> Result<Option<ITunesInfos>, MusicError> {
         let query = format!("{} {}", props.artist, props.name);
 
-        if let Some(infos) = self.cache.get(query.clone()) {
-            return Ok(Some(infos.to_owned()));
+        match self.cache.get(&query) {
+            CacheLookup::Found(infos) => {
+                self.metrics.record_cache_hit();
+                return Ok(Some(infos.to_owned()));
+            }
+            CacheLookup::NotFound => {
+                self.metrics.record_cache_hit();
+                return Ok(None);
+            }
+            CacheLookup::Absent => {
+                self.metrics.record_cache_miss();
+            }
         }
 
-        let params = if album {
-            vec![
-                ("media", "music"),
-                ("limit", "1"),
-                ("term", &query),
-                ("entity", "album"),
-            ]
-        } else {
-            vec![("media", "music"), ("limit", "1"), ("term", &query)]
-        };
-
-        let url = Url::parse_with_params("https://itunes.apple.com/search?", &params)?;
-        let resp: ResponseOuter = reqwest::get(url.as_str()).await?.json().await?;
+        let mut saw_error = false;
 
-        if resp.results.is_empty() {
-            if album {
-                return Box::pin(self.search_album_artwork(props, false)).await;
-            } else {
-                return Ok(None);
+        for provider in &self.providers {
+            match provider.lookup(props).await {
+                Ok(Some(infos)) => {
+                    self.cache.set(query, infos.clone());
+                    return Ok(Some(infos));
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    eprintln!("Artwork provider lookup failed: {}", e);
+                    saw_error = true;
+                }
             }
         }
 
-        let res = &resp.results[0];
-        let artwork = if res.artwork_url_600.is_some() {
-            res.artwork_url_600.clone()
-        } else {
-            res.artwork_url_100.clone()
-        };
-
-        let infos = ITunesInfos {
-            artwork: artwork,
-            url: res.collection_view_url.clone(),
-        };
+        // Only remember a genuine "no artwork anywhere" result. A
+        // transient network error isn't evidence of that, and caching it
+        // would suppress lookups for the full negative TTL.
+        if !saw_error {
+            self.cache.set_not_found(query);
+        }
 
-        self.cache.set(query, infos.clone());
-        Ok(Some(infos))
+        Ok(None)
     }
 
     async fn update_presence(&mut self) -> Result<AppState, MusicError> {
         let state: String = execute_script(&self.app_name, SCRIPTS.get_state)?;
 
-        if state != "playing" {
+        if state != "playing" && state != "paused" {
+            self.last_signature = None;
+            self.last_presence = None;
             return Ok(AppState::Idle);
         }
 
         let props: ITunesProps = execute_script(&self.app_name, SCRIPTS.get_props)?;
+        let signature = TrackSignature::new(&props, &state);
+
+        if self.last_signature.as_ref() == Some(&signature) {
+            if state == "paused" {
+                // Paused and nothing about the track changed: there are no
+                // timestamps to drift, so there's nothing to update.
+                return Ok(AppState::Unchanged);
+            }
+
+            if let Some(last) = self.last_presence.clone() {
+                if let (Some(duration), Some(start)) = (props.duration, last.start) {
+                    let player_position: f64 =
+                        execute_script(&self.app_name, SCRIPTS.get_position)?;
+                    let expected_position = expected_position_secs(start);
+
+                    if (player_position - expected_position).abs()
+                        > POSITION_DRIFT_TOLERANCE_SECS
+                    {
+                        let mut presence_data = last;
+                        let (start, end) = timing_from_position(player_position, duration);
+                        presence_data.set_timing(start, end);
+                        self.last_presence = Some(presence_data.clone());
+                        return Ok(AppState::Presence(presence_data));
+                    }
+                }
+
+                return Ok(AppState::Unchanged);
+            }
+        }
+
         let mut presence_data = PresenceData::new(&props);
 
-        if let Some(duration) = props.duration {
-            let player_position: f64 = execute_script(&self.app_name, SCRIPTS.get_position)?;
-            let current_time = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .expect("Time went backwards");
+        let reused_artwork = self.last_presence.as_ref().filter(|last| {
+            last.name == props.name && last.artist == props.artist && last.album == props.album
+        });
 
-            let start = current_time.as_secs() as i64 * 1000 - (player_position * 1000.0) as i64;
-            let end = start + (duration * 1000.0) as i64;
+        if let Some(reused) = reused_artwork {
+            presence_data.artwork_url = reused.artwork_url.clone();
+            presence_data.share_url = reused.share_url.clone();
+            presence_data.share_source = reused.share_source;
+        } else if let Ok(Some(infos)) = self.search_album_artwork(&props).await {
+            // No album gate here: Deezer and the Cover Art Archive match on
+            // artist+track and don't need one, so album-less local files
+            // still reach those fallbacks. Only the iTunes provider's
+            // album-first attempt cares, and it already falls back to a
+            // song-only search internally.
+            presence_data.set_artwork_info(infos);
+        }
 
-            presence_data.set_timing(start, end);
+        self.last_signature = Some(signature);
+
+        if state == "paused" {
+            self.last_presence = Some(presence_data.clone());
+            return Ok(AppState::Paused(presence_data));
         }
 
-        if !props.album.is_empty() {
-            if let Ok(Some(infos)) = self.search_album_artwork(&props, true).await {
-                presence_data.set_artwork_info(infos);
-            }
+        if let Some(duration) = props.duration {
+            let player_position: f64 = execute_script(&self.app_name, SCRIPTS.get_position)?;
+            let (start, end) = timing_from_position(player_position, duration);
+            presence_data.set_timing(start, end);
         }
 
+        self.last_presence = Some(presence_data.clone());
+
         Ok(AppState::Presence(presence_data))
     }
 
@@ -142,10 +212,13 @@ impl App {
                 return Ok(false);
             }
             self.state = AppState::Idle;
+            self.last_signature = None;
+            self.last_presence = None;
             return Ok(true);
         }
 
         match self.update_presence().await? {
+            AppState::Unchanged => Ok(true),
             AppState::Idle => {
                 if let Err(e) = self.client.clear_activity() {
                     eprintln!("Failed to clear activity: {}", e);
@@ -175,13 +248,20 @@ impl App {
                 let assets = activity::Assets::new().large_image(&artwork);
                 activity_builder = activity_builder.assets(assets);
 
-                if let Some(url) = &data.share_url {
-                    activity_builder = activity_builder
-                        .buttons(vec![activity::Button::new("Listen on Apple Music", url)]);
+                if self.config.show_apple_music_button {
+                    if let Some(url) = &data.share_url {
+                        let label = data
+                            .share_source
+                            .map(|s| s.button_label())
+                            .unwrap_or(ArtworkSource::ITunes.button_label());
+                        activity_builder =
+                            activity_builder.buttons(vec![activity::Button::new(label, url)]);
+                    }
                 }
 
                 match self.client.set_activity(activity_builder) {
                     Ok(_) => {
+                        self.metrics.record_scrobble();
                         self.state = AppState::Presence(data);
                         Ok(true)
                     }
@@ -191,6 +271,47 @@ impl App {
                     }
                 }
             }
+            AppState::Paused(data) => {
+                let mut activity_builder = activity::Activity::new()
+                    .details(&data.name)
+                    .activity_type(activity::ActivityType::Listening);
+
+                if !data.artist.is_empty() {
+                    activity_builder = activity_builder.state(&data.artist);
+                }
+
+                let artwork = data
+                    .artwork_url
+                    .clone()
+                    .unwrap_or_else(|| "appicon".to_string());
+                let assets = activity::Assets::new()
+                    .large_image(&artwork)
+                    .small_image("paused")
+                    .small_text("Paused");
+                activity_builder = activity_builder.assets(assets);
+
+                if self.config.show_apple_music_button {
+                    if let Some(url) = &data.share_url {
+                        let label = data
+                            .share_source
+                            .map(|s| s.button_label())
+                            .unwrap_or(ArtworkSource::ITunes.button_label());
+                        activity_builder =
+                            activity_builder.buttons(vec![activity::Button::new(label, url)]);
+                    }
+                }
+
+                match self.client.set_activity(activity_builder) {
+                    Ok(_) => {
+                        self.state = AppState::Paused(data);
+                        Ok(true)
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to set activity: {}", e);
+                        Ok(false)
+                    }
+                }
+            }
         }
     }
 
@@ -214,7 +335,9 @@ impl App {
             }
         });
 
-        let client_id = DISCORD_CLIENT_ID.to_string();
+        let client_id = self.config.discord_client_id.clone();
+        let poll_interval = self.config.poll_interval();
+        let reconnect_delay = self.config.reconnect_delay();
         let mut connected = false;
 
         while running.load(Ordering::SeqCst) {
@@ -223,25 +346,27 @@ impl App {
                     connected = true;
                     println!("Connected to Discord!");
                 } else {
-                    tokio::time::sleep(Duration::from_secs(15)).await;
+                    tokio::time::sleep(reconnect_delay).await;
                     continue;
                 }
             }
 
             match self.handle_state().await {
                 Ok(true) => {
-                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    self.metrics.maybe_push().await;
+                    tokio::time::sleep(poll_interval).await;
                 }
                 Ok(false) => {
                     println!(
-                        "Lost connection to Discord, attempting to reconnect in 15 seconds..."
+                        "Lost connection to Discord, attempting to reconnect in {} seconds...",
+                        reconnect_delay.as_secs()
                     );
                     connected = false;
-                    tokio::time::sleep(Duration::from_secs(15)).await;
+                    tokio::time::sleep(reconnect_delay).await;
                 }
                 Err(e) => {
                     eprintln!("Error: {}", e);
-                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    tokio::time::sleep(poll_interval).await;
                 }
             }
         }
@@ -266,6 +391,7 @@ async fn main() -> Result<(), MusicError> {
         ITunesAppName::ITunes
     };
 
-    let mut app = App::new(DISCORD_CLIENT_ID, app_name)?;
+    let config = Config::load()?;
+    let mut app = App::new(config, app_name)?;
     app.run().await
 }
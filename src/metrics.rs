@@ -0,0 +1,188 @@
+//! Feature-gated listening statistics, pushed to Redis or a Prometheus
+//! Pushgateway on an interval. When the `metrics` feature is disabled every
+//! call in this module is a zero-cost no-op, so `App`'s hot loop is
+//! unaffected.
+
+#[cfg(feature = "metrics")]
+mod imp {
+    use serde::Serialize;
+    use std::env;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+
+    const PUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+    #[derive(Debug, Serialize)]
+    struct MetricsSnapshot {
+        hostname: String,
+        scrobbles: u64,
+        reconnects: u64,
+        cache_hits: u64,
+        cache_misses: u64,
+        uptime_secs: u64,
+    }
+
+    pub struct Metrics {
+        scrobbles: AtomicU64,
+        reconnects: AtomicU64,
+        cache_hits: AtomicU64,
+        cache_misses: AtomicU64,
+        started_at: Instant,
+        // `None` means "never pushed yet"; avoids subtracting from a
+        // monotonic clock that may be only seconds past boot.
+        last_push: Mutex<Option<Instant>>,
+    }
+
+    impl Default for Metrics {
+        fn default() -> Self {
+            Self {
+                scrobbles: AtomicU64::new(0),
+                reconnects: AtomicU64::new(0),
+                cache_hits: AtomicU64::new(0),
+                cache_misses: AtomicU64::new(0),
+                started_at: Instant::now(),
+                last_push: Mutex::new(None),
+            }
+        }
+    }
+
+    impl Metrics {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        pub fn record_scrobble(&self) {
+            self.scrobbles.fetch_add(1, Ordering::Relaxed);
+        }
+
+        pub fn record_reconnect(&self) {
+            self.reconnects.fetch_add(1, Ordering::Relaxed);
+        }
+
+        pub fn record_cache_hit(&self) {
+            self.cache_hits.fetch_add(1, Ordering::Relaxed);
+        }
+
+        pub fn record_cache_miss(&self) {
+            self.cache_misses.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn snapshot(&self) -> MetricsSnapshot {
+            MetricsSnapshot {
+                hostname: hostname(),
+                scrobbles: self.scrobbles.load(Ordering::Relaxed),
+                reconnects: self.reconnects.load(Ordering::Relaxed),
+                cache_hits: self.cache_hits.load(Ordering::Relaxed),
+                cache_misses: self.cache_misses.load(Ordering::Relaxed),
+                uptime_secs: self.started_at.elapsed().as_secs(),
+            }
+        }
+
+        /// Pushes a snapshot to whichever endpoint is configured via env
+        /// vars, throttled to at most once per `PUSH_INTERVAL`. Failures are
+        /// swallowed; metrics reporting must never take the presence loop
+        /// down.
+        pub async fn maybe_push(&self) {
+            {
+                let mut last = self.last_push.lock().unwrap();
+                if last.is_some_and(|last| last.elapsed() < PUSH_INTERVAL) {
+                    return;
+                }
+                *last = Some(Instant::now());
+            }
+
+            let snapshot = self.snapshot();
+
+            if let Ok(url) = env::var("METRICS_PUSHGATEWAY_URL") {
+                if let Err(e) = push_prometheus(&url, &snapshot).await {
+                    eprintln!("Failed to push metrics to Pushgateway: {}", e);
+                }
+            }
+
+            if let Ok(url) = env::var("METRICS_REDIS_URL") {
+                if let Err(e) = push_redis(&url, &snapshot).await {
+                    eprintln!("Failed to push metrics to Redis: {}", e);
+                }
+            }
+        }
+    }
+
+    fn hostname() -> String {
+        hostname::get()
+            .ok()
+            .and_then(|h| h.into_string().ok())
+            .unwrap_or_else(|| "unknown".to_string())
+    }
+
+    async fn push_prometheus(url: &str, snapshot: &MetricsSnapshot) -> Result<(), reqwest::Error> {
+        let body = format!(
+            "apple_music_rpc_scrobbles {}\n\
+             apple_music_rpc_reconnects {}\n\
+             apple_music_rpc_cache_hits {}\n\
+             apple_music_rpc_cache_misses {}\n\
+             apple_music_rpc_uptime_seconds {}\n",
+            snapshot.scrobbles,
+            snapshot.reconnects,
+            snapshot.cache_hits,
+            snapshot.cache_misses,
+            snapshot.uptime_secs,
+        );
+
+        reqwest::Client::new()
+            .post(format!(
+                "{}/metrics/job/apple-music-rpc/instance/{}",
+                url.trim_end_matches('/'),
+                snapshot.hostname
+            ))
+            .body(body)
+            .send()
+            .await?;
+
+        Ok(())
+    }
+
+    async fn push_redis(url: &str, snapshot: &MetricsSnapshot) -> Result<(), redis::RedisError> {
+        let client = redis::Client::open(url)?;
+        let mut conn = client.get_multiplexed_async_connection().await?;
+        let payload = serde_json::to_string(snapshot).unwrap_or_default();
+
+        redis::cmd("SET")
+            .arg(format!("apple-music-rpc:{}", snapshot.hostname))
+            .arg(payload)
+            .query_async::<()>(&mut conn)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "metrics"))]
+mod imp {
+    #[derive(Default)]
+    pub struct Metrics;
+
+    impl Metrics {
+        #[inline]
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        #[inline]
+        pub fn record_scrobble(&self) {}
+
+        #[inline]
+        pub fn record_reconnect(&self) {}
+
+        #[inline]
+        pub fn record_cache_hit(&self) {}
+
+        #[inline]
+        pub fn record_cache_miss(&self) {}
+
+        #[inline]
+        pub async fn maybe_push(&self) {}
+    }
+}
+
+pub use imp::Metrics;
@@ -0,0 +1,189 @@
+//! Pluggable artwork lookups. `App::search_album_artwork` tries each
+//! configured [`ArtworkProvider`] in priority order and caches whichever
+//! one succeeds, so tracks the iTunes Search API doesn't index still get
+//! cover art and a share URL.
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use url::Url;
+
+use crate::structs::{ArtworkSource, ITunesInfos, ITunesProps, MusicError, ResponseOuter};
+
+#[async_trait]
+pub trait ArtworkProvider: Send + Sync {
+    async fn lookup(&self, props: &ITunesProps) -> Result<Option<ITunesInfos>, MusicError>;
+}
+
+/// The original iTunes Search API lookup, trying an album match before a
+/// song match (or the reverse, if `prefer_album` is `false`).
+pub struct ITunesProvider {
+    prefer_album: bool,
+}
+
+impl ITunesProvider {
+    pub fn new(prefer_album: bool) -> Self {
+        Self { prefer_album }
+    }
+}
+
+#[async_trait]
+impl ArtworkProvider for ITunesProvider {
+    async fn lookup(&self, props: &ITunesProps) -> Result<Option<ITunesInfos>, MusicError> {
+        if let Some(infos) = search_itunes(props, self.prefer_album).await? {
+            return Ok(Some(infos));
+        }
+        search_itunes(props, !self.prefer_album).await
+    }
+}
+
+async fn search_itunes(props: &ITunesProps, album: bool) -> Result<Option<ITunesInfos>, MusicError> {
+    let query = format!("{} {}", props.artist, props.name);
+    let params = if album {
+        vec![
+            ("media", "music"),
+            ("limit", "1"),
+            ("term", query.as_str()),
+            ("entity", "album"),
+        ]
+    } else {
+        vec![("media", "music"), ("limit", "1"), ("term", query.as_str())]
+    };
+
+    let url = Url::parse_with_params("https://itunes.apple.com/search?", &params)?;
+    let resp: ResponseOuter = reqwest::get(url.as_str()).await?.json().await?;
+
+    let Some(res) = resp.results.into_iter().next() else {
+        return Ok(None);
+    };
+
+    let artwork = res.artwork_url_600.or(res.artwork_url_100);
+
+    Ok(Some(ITunesInfos {
+        artwork,
+        url: res.collection_view_url,
+        source: ArtworkSource::ITunes,
+    }))
+}
+
+/// Queries Deezer's public search API, which has broader coverage of
+/// regional and independent releases than the iTunes Search API.
+pub struct DeezerProvider;
+
+#[derive(Debug, Deserialize)]
+struct DeezerResponse {
+    data: Vec<DeezerTrack>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeezerTrack {
+    album: DeezerAlbum,
+    link: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeezerAlbum {
+    cover_xl: Option<String>,
+}
+
+#[async_trait]
+impl ArtworkProvider for DeezerProvider {
+    async fn lookup(&self, props: &ITunesProps) -> Result<Option<ITunesInfos>, MusicError> {
+        let query = format!("artist:\"{}\" track:\"{}\"", props.artist, props.name);
+        let url = Url::parse_with_params("https://api.deezer.com/search", &[("q", query.as_str())])?;
+
+        let resp: DeezerResponse = reqwest::get(url.as_str()).await?.json().await?;
+
+        Ok(resp.data.into_iter().next().map(|track| ITunesInfos {
+            artwork: track.album.cover_xl,
+            url: track.link,
+            source: ArtworkSource::Deezer,
+        }))
+    }
+}
+
+/// Queries MusicBrainz for a matching release, then reads cover art from
+/// the Cover Art Archive. The last resort for obscure releases neither
+/// iTunes nor Deezer index.
+pub struct CoverArtArchiveProvider;
+
+#[derive(Debug, Deserialize)]
+struct MusicBrainzResponse {
+    releases: Vec<MusicBrainzRelease>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MusicBrainzRelease {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CoverArtResponse {
+    images: Vec<CoverArtImage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CoverArtImage {
+    image: String,
+    front: bool,
+}
+
+#[async_trait]
+impl ArtworkProvider for CoverArtArchiveProvider {
+    async fn lookup(&self, props: &ITunesProps) -> Result<Option<ITunesInfos>, MusicError> {
+        let query = format!("artist:\"{}\" AND recording:\"{}\"", props.artist, props.name);
+        let url = Url::parse_with_params(
+            "https://musicbrainz.org/ws/2/release",
+            &[("query", query.as_str()), ("fmt", "json"), ("limit", "1")],
+        )?;
+
+        let client = reqwest::Client::builder()
+            .user_agent("apple-music-rpc")
+            .build()?;
+
+        let resp: MusicBrainzResponse = client.get(url.as_str()).send().await?.json().await?;
+
+        let Some(release) = resp.releases.into_iter().next() else {
+            return Ok(None);
+        };
+
+        let cover_url = format!("https://coverartarchive.org/release/{}", release.id);
+        let cover_resp = match client.get(&cover_url).send().await {
+            Ok(resp) if resp.status().is_success() => resp,
+            _ => return Ok(None),
+        };
+
+        let cover: CoverArtResponse = match cover_resp.json().await {
+            Ok(cover) => cover,
+            Err(_) => return Ok(None),
+        };
+
+        let artwork = cover.images.into_iter().find(|img| img.front).map(|img| img.image);
+
+        Ok(artwork.map(|artwork| ITunesInfos {
+            artwork: Some(artwork),
+            url: Some(format!("https://musicbrainz.org/release/{}", release.id)),
+            source: ArtworkSource::CoverArtArchive,
+        }))
+    }
+}
+
+/// Builds the provider chain in priority order. Defaults to iTunes,
+/// Deezer, then the Cover Art Archive, but the order (and which providers
+/// run at all) can be overridden at runtime with a comma-separated
+/// `ARTWORK_PROVIDER_ORDER` env var, e.g. `"deezer,itunes"`. `prefer_album`
+/// controls whether the iTunes provider tries an album or song match
+/// first, per the user's `Config`.
+pub fn configured_providers(prefer_album: bool) -> Vec<Box<dyn ArtworkProvider>> {
+    let order = std::env::var("ARTWORK_PROVIDER_ORDER")
+        .unwrap_or_else(|_| "itunes,deezer,coverart".to_string());
+
+    order
+        .split(',')
+        .filter_map(|name| match name.trim() {
+            "itunes" => Some(Box::new(ITunesProvider::new(prefer_album)) as Box<dyn ArtworkProvider>),
+            "deezer" => Some(Box::new(DeezerProvider) as Box<dyn ArtworkProvider>),
+            "coverart" => Some(Box::new(CoverArtArchiveProvider) as Box<dyn ArtworkProvider>),
+            _ => None,
+        })
+        .collect()
+}
@@ -25,10 +25,31 @@ pub struct ITunesProps {
     pub duration: Option<f64>,
 }
 
+/// Which [`ArtworkProvider`](crate::providers::ArtworkProvider) an
+/// [`ITunesInfos`] result came from, so the caller can label a share-url
+/// button correctly instead of always saying "Apple Music".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArtworkSource {
+    ITunes,
+    Deezer,
+    CoverArtArchive,
+}
+
+impl ArtworkSource {
+    pub fn button_label(&self) -> &'static str {
+        match self {
+            ArtworkSource::ITunes => "Listen on Apple Music",
+            ArtworkSource::Deezer => "Listen on Deezer",
+            ArtworkSource::CoverArtArchive => "View on MusicBrainz",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ITunesInfos {
     pub artwork: Option<String>,
     pub url: Option<String>,
+    pub source: ArtworkSource,
 }
 
 #[derive(Debug, Deserialize)]
@@ -66,6 +87,7 @@ pub enum MusicError {
     UrlParseError(url::ParseError),
     CacheError(String),
     DiscordError(String),
+    ConfigError(String),
 }
 
 impl fmt::Display for MusicError {
@@ -78,6 +100,7 @@ impl fmt::Display for MusicError {
             MusicError::UrlParseError(e) => write!(f, "URL parse error: {}", e),
             MusicError::CacheError(e) => write!(f, "Cache error: {}", e),
             MusicError::DiscordError(e) => write!(f, "Discord error: {}", e),
+            MusicError::ConfigError(e) => write!(f, "Config error: {}", e),
         }
     }
 }
@@ -120,14 +143,20 @@ impl From<CacheError> for MusicError {
     }
 }
 
+impl From<toml::de::Error> for MusicError {
+    fn from(err: toml::de::Error) -> Self {
+        MusicError::ConfigError(err.to_string())
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PresenceData {
     pub name: String,
     pub artist: String,
-    #[allow(dead_code)]
     pub album: String,
     pub artwork_url: Option<String>,
     pub share_url: Option<String>,
+    pub share_source: Option<ArtworkSource>,
     pub start: Option<i64>,
     pub end: Option<i64>,
 }
@@ -140,6 +169,7 @@ impl PresenceData {
             album: props.album.clone(),
             artwork_url: None,
             share_url: None,
+            share_source: None,
             start: None,
             end: None,
         }
@@ -153,6 +183,7 @@ impl PresenceData {
     pub fn set_artwork_info(&mut self, infos: ITunesInfos) {
         self.artwork_url = infos.artwork;
         self.share_url = infos.url;
+        self.share_source = Some(infos.source);
     }
 }
 
@@ -160,6 +191,35 @@ impl PresenceData {
 pub enum AppState {
     Idle,
     Presence(PresenceData),
+    /// The player is paused: track details and artwork are kept, but
+    /// timestamps are dropped since playback isn't progressing.
+    Paused(PresenceData),
+    /// Nothing changed since the last tick: the track signature and the
+    /// interpolated playback position both still match, so the caller
+    /// should leave the existing Discord activity untouched.
+    Unchanged,
+}
+
+/// Identifies "the same thing is playing" without caring about timestamps,
+/// so a per-second poll can tell a genuine track change from normal
+/// progress through the current one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackSignature {
+    pub name: String,
+    pub artist: String,
+    pub album: String,
+    pub player_state: String,
+}
+
+impl TrackSignature {
+    pub fn new(props: &ITunesProps, player_state: &str) -> Self {
+        Self {
+            name: props.name.clone(),
+            artist: props.artist.clone(),
+            album: props.album.clone(),
+            player_state: player_state.to_string(),
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -192,3 +252,8 @@ pub const SCRIPTS: ScriptCollection = ScriptCollection {
 
 pub const MAC_OS_CATALINA: f32 = 10.15;
 pub const DISCORD_CLIENT_ID: &str = "1326053171809747006";
+
+/// How far (in seconds) the reported player position may drift from the
+/// interpolated position before we treat it as a seek and recompute the
+/// presence timestamps.
+pub const POSITION_DRIFT_TOLERANCE_SECS: f64 = 2.0;
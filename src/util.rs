@@ -1,5 +1,6 @@
 use osascript::JavaScript;
 use serde::Deserialize;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::{ITunesAppName, MusicError, ScriptParams, MAC_OS_CATALINA};
 
@@ -34,3 +35,26 @@ where
         .execute_with_params(params)
         .map_err(MusicError::from)
 }
+
+/// Derives Discord presence `(start, end)` timestamps, in milliseconds,
+/// from a player position reported in seconds.
+pub fn timing_from_position(position_secs: f64, duration_secs: f64) -> (i64, i64) {
+    let now_ms = now_ms();
+    let start = now_ms - (position_secs * 1000.0) as i64;
+    let end = start + (duration_secs * 1000.0) as i64;
+
+    (start, end)
+}
+
+/// Where playback should be, in seconds, if it has progressed steadily
+/// since `start` (a presence timestamp in milliseconds) with no seeking.
+pub fn expected_position_secs(start_ms: i64) -> f64 {
+    (now_ms() - start_ms) as f64 / 1000.0
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_millis() as i64
+}